@@ -0,0 +1,249 @@
+//! A small two-pass assembler that turns mnemonic source into the raw
+//! `u16` words `Sma16::load_memory` expects.
+//!
+//! Each line is one of:
+//!
+//! ```text
+//! label:                  ; defines a label at the current address
+//! label: MNEMONIC operand ; a label and an instruction on one line
+//! MNEMONIC operand        ; an instruction, operand is a literal or label
+//! .org 0x010              ; moves the address cursor
+//! .word 0x1234            ; emits a raw word
+//! ```
+//!
+//! `;` starts a comment that runs to the end of the line. Labels are
+//! resolved in a second pass once every address is known, so forward
+//! references work.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+const MNEMONICS: &[(&str, u16)] = &[
+    ("HALT",  0x0),
+    ("JUMP",  0x2),
+    ("JUMPZ", 0x3),
+    ("LOAD",  0x4),
+    ("STORE", 0x5),
+    ("LSHFT", 0x6),
+    ("RSHFT", 0x7),
+    ("XOR",   0x8),
+    ("AND",   0x9),
+    ("SFULL", 0xA),
+    ("ADD",   0xB),
+    ("POP",   0xD),
+    ("PUSH",  0xE),
+    ("NOOP",  0xF),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssembleError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    OperandOutOfRange { line: usize, token: String, max: u16 },
+    InvalidOperand { line: usize, token: String },
+    MissingOperand { line: usize },
+    UndefinedLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use AssembleError::*;
+        match self {
+            UnknownMnemonic { line, mnemonic } =>
+                write!(f, "line {}: unknown mnemonic `{}`", line, mnemonic),
+            OperandOutOfRange { line, token, max } =>
+                write!(f, "line {}: operand `{}` out of range (max 0x{:X})", line, token, max),
+            InvalidOperand { line, token } =>
+                write!(f, "line {}: invalid operand `{}`", line, token),
+            MissingOperand { line } =>
+                write!(f, "line {}: expected an operand", line),
+            UndefinedLabel { line, label } =>
+                write!(f, "line {}: undefined label `{}`", line, label),
+            DuplicateLabel { line, label } =>
+                write!(f, "line {}: label `{}` defined more than once", line, label),
+        }
+    }
+}
+
+impl core::error::Error for AssembleError {}
+
+enum Operand<'a> {
+    Literal(u16),
+    Label(&'a str),
+}
+
+enum Item<'a> {
+    Instruction { address: u16, opcode: u16, operand: Operand<'a>, line: usize },
+    Word { address: u16, operand: Operand<'a>, line: usize },
+}
+
+impl Item<'_> {
+    fn address(&self) -> u16 {
+        match self {
+            Item::Instruction { address, .. } => *address,
+            Item::Word { address, .. } => *address,
+        }
+    }
+}
+
+fn parse_number(token: &str) -> Option<u16> {
+    if let Some(hex) = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        token.parse().ok()
+    }
+}
+
+/// A token starting with a digit or `0x`/`0X` is meant as a number, even if
+/// it doesn't fit in a `u16` — treating it as a label name instead would
+/// silently swallow the mistake as an `UndefinedLabel` error.
+fn looks_numeric(token: &str) -> bool {
+    token.starts_with("0x") || token.starts_with("0X") || token.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn parse_operand(token: &str, line: usize) -> Result<Operand<'_>, AssembleError> {
+    match parse_number(token) {
+        Some(n) => Ok(Operand::Literal(n)),
+        None if looks_numeric(token) => Err(AssembleError::InvalidOperand {
+            line,
+            token: token.to_string(),
+        }),
+        None => Ok(Operand::Label(token)),
+    }
+}
+
+fn resolve(operand: &Operand, max: u16, line: usize, labels: &BTreeMap<&str, u16>) -> Result<u16, AssembleError> {
+    let value = match operand {
+        Operand::Literal(n) => *n,
+        Operand::Label(name) => *labels.get(name).ok_or_else(|| AssembleError::UndefinedLabel {
+            line,
+            label: name.to_string(),
+        })?,
+    };
+    if value > max {
+        let token = match operand {
+            Operand::Literal(n) => format!("0x{:X}", n),
+            Operand::Label(name) => name.to_string(),
+        };
+        return Err(AssembleError::OperandOutOfRange { line, token, max });
+    }
+    Ok(value)
+}
+
+/// Assembles `src` into a flat array of words, ready for
+/// `Sma16::load_memory`. The returned `Vec` starts at the lowest address
+/// any instruction or `.word` was placed at (`0x000` if there is no
+/// `.org`) and runs to the highest address written, with any gaps left as
+/// zero. That start address is not part of the result.
+pub fn assemble(src: &str) -> Result<Vec<u16>, AssembleError> {
+    let mut labels: BTreeMap<&str, u16> = BTreeMap::new();
+    let mut items = Vec::new();
+    let mut address: u16 = 0;
+
+    for (line_no, raw_line) in src.lines().enumerate() {
+        let line = line_no + 1;
+        let code = match raw_line.find(';') {
+            Some(i) => &raw_line[..i],
+            None => raw_line,
+        };
+        let mut code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+
+        if let Some(colon) = code.find(':') {
+            let label = code[..colon].trim();
+            if labels.insert(label, address).is_some() {
+                return Err(AssembleError::DuplicateLabel { line, label: label.to_string() });
+            }
+            code = code[colon + 1..].trim();
+            if code.is_empty() {
+                continue;
+            }
+        }
+
+        let mut tokens = code.split_whitespace();
+        let keyword = tokens.next().unwrap();
+
+        if keyword.eq_ignore_ascii_case(".org") {
+            let token = tokens.next().ok_or(AssembleError::MissingOperand { line })?;
+            address = parse_number(token).ok_or_else(|| AssembleError::InvalidOperand {
+                line,
+                token: token.to_string(),
+            })?;
+            continue;
+        }
+
+        if keyword.eq_ignore_ascii_case(".word") {
+            let token = tokens.next().ok_or(AssembleError::MissingOperand { line })?;
+            items.push(Item::Word { address, operand: parse_operand(token, line)?, line });
+        } else {
+            let opcode = MNEMONICS
+                .iter()
+                .find(|(name, _)| keyword.eq_ignore_ascii_case(name))
+                .map(|(_, opcode)| *opcode)
+                .ok_or_else(|| AssembleError::UnknownMnemonic {
+                    line,
+                    mnemonic: keyword.to_string(),
+                })?;
+            let operand = match tokens.next() {
+                Some(token) => parse_operand(token, line)?,
+                None => Operand::Literal(0),
+            };
+            items.push(Item::Instruction { address, opcode, operand, line });
+        }
+
+        address = address.wrapping_add(1);
+    }
+
+    let (base, top) = items.iter().fold((u16::MAX, 0u16), |(lowest, highest), item| {
+        (lowest.min(item.address()), highest.max(item.address()))
+    });
+    let base = if items.is_empty() { 0 } else { base };
+    let mut words = vec![0u16; (top.wrapping_sub(base) as usize) + if items.is_empty() { 0 } else { 1 }];
+
+    for item in &items {
+        let index = (item.address().wrapping_sub(base)) as usize;
+        words[index] = match item {
+            Item::Instruction { opcode, operand, line, .. } => {
+                let data = resolve(operand, 0x0FFF, *line, &labels)?;
+                (opcode << 12) | data
+            }
+            Item::Word { operand, line, .. } => resolve(operand, 0xFFFF, *line, &labels)?,
+        };
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backward_org_does_not_panic() {
+        let words = assemble(".org 0x100\nNOOP\n.org 0x050\nNOOP\n").unwrap();
+        assert_eq!(words.len(), 0x100 - 0x050 + 1);
+        assert_eq!(words[0], 0xF000);
+        assert_eq!(words[0x100 - 0x050], 0xF000);
+    }
+
+    #[test]
+    fn out_of_range_literal_is_an_invalid_operand() {
+        let err = assemble(".word 0x10000").unwrap_err();
+        assert_eq!(err, AssembleError::InvalidOperand { line: 1, token: "0x10000".to_string() });
+
+        let err = assemble(".word 99999").unwrap_err();
+        assert_eq!(err, AssembleError::InvalidOperand { line: 1, token: "99999".to_string() });
+    }
+
+    #[test]
+    fn undefined_label_is_still_reported_as_a_label() {
+        let err = assemble("JUMP missing").unwrap_err();
+        assert_eq!(err, AssembleError::UndefinedLabel { line: 1, label: "missing".to_string() });
+    }
+}
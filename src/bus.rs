@@ -0,0 +1,51 @@
+//! Memory-mapped I/O is dispatched through a [`Bus`] instead of the core
+//! talking to the host directly, so the VM can be embedded, tested, or
+//! retargeted without dragging `std` along.
+
+use alloc::vec::Vec;
+
+/// The host-side end of the VM's memory-mapped I/O.
+///
+/// `output` is called once per byte written to `ASCII_OUT`/`SMALL_OUT`;
+/// `input` defaults to "nothing available" for buses that are output-only;
+/// the VM itself does not call it.
+pub trait Bus {
+    fn output(&mut self, byte: u8);
+
+    fn input(&mut self) -> Option<u8> {
+        None
+    }
+}
+
+/// Writes every output byte straight to stdout, flushing after each one.
+/// This is the bus `Sma16` used implicitly before I/O was made pluggable.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StdoutBus;
+
+#[cfg(feature = "std")]
+impl Bus for StdoutBus {
+    fn output(&mut self, byte: u8) {
+        use std::io::Write;
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&[byte]).expect("error writing to stdout");
+        stdout.flush().expect("error flushing stdout");
+    }
+}
+
+/// Captures every output byte in order instead of writing it anywhere,
+/// for use in tests that need to assert on what a program printed.
+#[derive(Debug, Default)]
+pub struct VecBus(pub Vec<u8>);
+
+impl VecBus {
+    pub fn new() -> VecBus {
+        VecBus(Vec::new())
+    }
+}
+
+impl Bus for VecBus {
+    fn output(&mut self, byte: u8) {
+        self.0.push(byte);
+    }
+}
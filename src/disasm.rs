@@ -0,0 +1,89 @@
+//! Turns raw memory words back into mnemonic assembly, mirroring the
+//! encoding rules in `Instruction` and `Word`.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{Instruction, Memory, Word};
+
+impl Instruction {
+    fn mnemonic(&self) -> Option<&'static str> {
+        use Instruction::*;
+        match self {
+            Halt      => Some("HALT"),
+            Jump      => Some("JUMP"),
+            JumpZ     => Some("JUMPZ"),
+            Load      => Some("LOAD"),
+            Store     => Some("STORE"),
+            LShft     => Some("LSHFT"),
+            RShft     => Some("RSHFT"),
+            Xor       => Some("XOR"),
+            And       => Some("AND"),
+            SFull     => Some("SFULL"),
+            Add       => Some("ADD"),
+            Pop       => Some("POP"),
+            Push      => Some("PUSH"),
+            NoOp      => Some("NOOP"),
+            Unknown(_) => None,
+        }
+    }
+}
+
+/// Decodes a single memory word into its mnemonic form, e.g. `ADD 0x00A`.
+///
+/// Unknown opcodes degrade gracefully into a `.word` directive holding the
+/// raw hex value rather than panicking, since disassembly has to cope with
+/// data words and not just code.
+pub fn disasm_word(word: u16) -> String {
+    let inst = Instruction::from(word.inst());
+    match inst.mnemonic() {
+        Some(mnemonic) => {
+            let mut out = format!("{} 0x{:03X}", mnemonic, word.data());
+            if let Instruction::LShft | Instruction::RShft = inst {
+                if word.data() & 1 == 1 {
+                    out.push_str(" ; masked");
+                } else {
+                    out.push_str(" ; full");
+                }
+            }
+            out
+        }
+        None => format!(".word 0x{:04X}", word),
+    }
+}
+
+/// Renders `len` words of `mem` starting at `start` into a listing, one
+/// disassembled instruction per line prefixed with its address.
+pub fn disassemble(mem: &Memory, start: u16, len: u16) -> String {
+    let mut out = String::new();
+    for offset in 0..len {
+        let address = start.wrapping_add(offset) & 0x0FFF;
+        let word = mem[address];
+        writeln!(out, "0x{:03X}: {}", address, disasm_word(word))
+            .expect("writing to a String cannot fail");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn known_opcode_round_trips_to_its_mnemonic() {
+        assert_eq!(disasm_word(0xB00A), "ADD 0x00A".to_string());
+    }
+
+    #[test]
+    fn unknown_opcode_falls_back_to_a_word_directive() {
+        assert_eq!(disasm_word(0x1234), ".word 0x1234".to_string());
+    }
+
+    #[test]
+    fn shift_mode_bit_is_annotated() {
+        assert_eq!(disasm_word(0x6001), "LSHFT 0x001 ; masked".to_string());
+        assert_eq!(disasm_word(0x6002), "LSHFT 0x002 ; full".to_string());
+    }
+}
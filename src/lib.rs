@@ -1,16 +1,24 @@
-use std::io::prelude::*;
-use std::io::stdout;
-use std::ops::{Index,IndexMut};
-use std::fmt;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+mod asm;
+mod bus;
+mod disasm;
+
+pub use asm::{assemble, AssembleError};
+pub use bus::{Bus, VecBus};
+#[cfg(feature = "std")]
+pub use bus::StdoutBus;
+pub use disasm::{disassemble, disasm_word};
 
 const MEM_MAX: usize = 4096;
 const STACK_MIN: usize = 16;
 
-fn putc(c: u8) {
-    stdout().write(&[c as u8]).expect("error writing to stdout");
-    stdout().flush().expect("error flushing stdout");
-}
-
 fn small_to_chars(small: u16) -> [Option<u8>; 2] {
     fn convert(small: u8) -> Option<u8> {
         match small {
@@ -110,24 +118,35 @@ impl IndexMut<u16> for Memory {
 
 impl fmt::Debug for Memory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        unimplemented!();
+        f.write_str(&disasm::disassemble(self, 0, MEM_MAX as u16))
     }
 }
 
 #[derive(Debug)]
-pub struct Sma16 {
+pub struct Sma16<B: Bus> {
     memory: Memory,
     stack: Vec<u16>,
+    stack_max: u16,
     program_counter: u16,
     instruction_register: u16,
     accumulator: u16,
     flag_halt: bool,
     flag_zero: bool,
+    timer_remaining: u16,
+    timer_reload: u16,
+    bus: B,
 }
 
-impl Sma16 {
+impl<B: Bus> Sma16<B> {
     const INTERRUPT_REASON_REGISTER: u16 = 0x008;
     const INTERRUPT_RETURN_REGISTER: u16 = 0x009;
+    const TIMER_REGISTER: u16            = 0x00C;
+    /// Writing this register sets the maximum stack depth `Push` will
+    /// allow (0xFFFF, effectively unbounded, by default); reading it back
+    /// gives the stack's current live depth, not the configured maximum.
+    /// `Store`'s read-modify-write (see `write_memory_data`) merges against
+    /// that live-depth read, so a program that sets the cap via `STORE`
+    /// rather than `SFULL` only ever gets the low 12 bits of it set.
     const STACK_SIZE_REGISTER: u16       = 0x00D;
 
     const RESET_VECTOR: u16    = 0x000;
@@ -135,22 +154,31 @@ impl Sma16 {
     const ASCII_OUT: u16       = 0x00A;
     const SMALL_OUT: u16       = 0x00B;
 
-    pub fn with_blank_memory() -> Sma16 {
-        Sma16::with_memory(Memory([0; MEM_MAX]))
+    /// Reason code `fault` is invoked with when the countdown timer wraps.
+    const TIMER_FAULT_REASON: u16 = 0x0001;
+    /// Reason code `fault` is invoked with when `Push` reaches `stack_max`.
+    const STACK_OVERFLOW_REASON: u16 = 0x0002;
+    /// Reason code `fault` is invoked with when `Pop` runs on an empty stack.
+    const STACK_UNDERFLOW_REASON: u16 = 0x0003;
+
+    pub fn with_blank_memory(bus: B) -> Sma16<B> {
+        Sma16::with_memory(Memory([0; MEM_MAX]), bus)
     }
 
-    pub fn with_memory(mem: Memory) -> Sma16 {
-        let mut sma16 = Sma16 {
+    pub fn with_memory(mem: Memory, bus: B) -> Sma16<B> {
+        Sma16 {
             memory: mem,
             stack: Vec::with_capacity(STACK_MIN),
-            program_counter: Sma16::RESET_VECTOR,
+            stack_max: 0xFFFF,
+            program_counter: Self::RESET_VECTOR,
             instruction_register: 0,
             accumulator: 0,
             flag_halt: false,
             flag_zero: false,
-        };
-        sma16.memory[Sma16::STACK_SIZE_REGISTER] = 0xFFFF;
-        sma16
+            timer_remaining: 0,
+            timer_reload: 0,
+            bus,
+        }
     }
 
     pub fn load_memory(&mut self, start: u16, mem: &[u16]) {
@@ -160,42 +188,70 @@ impl Sma16 {
     }
 
     pub fn reinitialize(&mut self) {
-        *self = Sma16::with_memory(self.memory);
+        self.stack.clear();
+        self.program_counter = Self::RESET_VECTOR;
+        self.instruction_register = 0;
+        self.accumulator = 0;
+        self.flag_halt = false;
+        self.flag_zero = false;
+        self.timer_remaining = 0;
+        self.timer_reload = 0;
+        self.stack_max = 0xFFFF;
     }
 
     pub fn fault(&mut self, reason: u16) {
-        self.memory[Sma16::INTERRUPT_RETURN_REGISTER] = self.program_counter + 1;
-        self.memory[Sma16::INTERRUPT_REASON_REGISTER] = reason;
-        self.program_counter = Sma16::FAULT_VECTOR;
+        self.memory[Self::INTERRUPT_RETURN_REGISTER] = self.program_counter + 1;
+        self.memory[Self::INTERRUPT_REASON_REGISTER] = reason;
+        self.program_counter = Self::FAULT_VECTOR;
     }
 
     pub fn read_memory(&self, address: u16) -> u16 {
-        self.memory[address.data()]
+        match address.data() {
+            Self::STACK_SIZE_REGISTER => self.stack.len() as u16,
+            masked => self.memory[masked],
+        }
     }
 
-    pub fn write_memory(&mut self, address: u16, value: u16) {
+    /// Writes `value` to `address`, dispatching through the bus if the
+    /// address is memory-mapped I/O. Returns whether it was.
+    pub fn write_memory(&mut self, address: u16, value: u16) -> bool {
         self.memory[address.data()] = value;
         match address {
-            Sma16::ASCII_OUT => putc((value & 0x00FF) as u8),
-            Sma16::SMALL_OUT => {
-                for c in &small_to_chars(value.data()) {
-                    c.map(putc);
+            Self::ASCII_OUT => {
+                self.bus.output((value & 0x00FF) as u8);
+                true
+            }
+            Self::SMALL_OUT => {
+                for c in small_to_chars(value.data()).iter().flatten() {
+                    self.bus.output(*c);
                 }
+                true
+            }
+            Self::TIMER_REGISTER => {
+                self.timer_reload = value;
+                self.timer_remaining = value;
+                false
             }
-            _ => {},
+            Self::STACK_SIZE_REGISTER => {
+                self.stack_max = value;
+                false
+            }
+            _ => false,
         }
     }
 
-    pub fn write_memory_data(&mut self, address: u16, value: u16) {
+    pub fn write_memory_data(&mut self, address: u16, value: u16) -> bool {
         self.write_memory(address, self.read_memory(address) & 0xF000 | value.data())
     }
 
-    pub fn step(&mut self) {
+    pub fn step(&mut self) -> StepResult {
         use Instruction::*;
         self.instruction_register = self.read_memory(self.program_counter);
+        let mut result = StepResult::Continue;
         let inc_pc_normally = match Instruction::from(self.instruction_register.inst()) {
             Halt => {
                 self.flag_halt = true;
+                result = StepResult::Halted;
                 true
             },
             Jump => {
@@ -213,7 +269,10 @@ impl Sma16 {
                 true
             },
             Store => {
-                self.write_memory_data(self.instruction_register.data(), self.accumulator.data());
+                let address = self.instruction_register.data();
+                if self.write_memory_data(address, self.accumulator.data()) {
+                    result = StepResult::Output(address);
+                }
                 true
             },
             LShft => {
@@ -243,7 +302,10 @@ impl Sma16 {
                 true
             },
             SFull => {
-                self.write_memory(self.instruction_register.data(), self.accumulator);
+                let address = self.instruction_register.data();
+                if self.write_memory(address, self.accumulator) {
+                    result = StepResult::Output(address);
+                }
                 true
             },
             Add => {
@@ -252,16 +314,33 @@ impl Sma16 {
                 true
             },
             Pop => {
-                self.accumulator = self.stack.pop().unwrap_or(0);
-                true
+                match self.stack.pop() {
+                    Some(value) => {
+                        self.accumulator = value;
+                        true
+                    }
+                    None => {
+                        self.fault(Self::STACK_UNDERFLOW_REASON);
+                        result = StepResult::Faulted(Self::STACK_UNDERFLOW_REASON);
+                        false
+                    }
+                }
             },
             Push => {
-                self.stack.push(self.accumulator);
-                true
+                if self.stack.len() as u16 >= self.stack_max {
+                    self.fault(Self::STACK_OVERFLOW_REASON);
+                    result = StepResult::Faulted(Self::STACK_OVERFLOW_REASON);
+                    false
+                } else {
+                    self.stack.push(self.accumulator);
+                    true
+                }
             },
             NoOp => true,
             Unknown(n) => {
-                self.fault(0x0ff0 | n.inst());
+                let reason = 0x0ff0 | n.inst();
+                self.fault(reason);
+                result = StepResult::Faulted(reason);
                 false
             },
         };
@@ -269,12 +348,116 @@ impl Sma16 {
         if inc_pc_normally {
             self.program_counter += 1;
         }
+
+        // An instruction fault already redirected the program counter and
+        // filled in the interrupt registers this step; don't let the timer
+        // clobber that with a fault of its own.
+        if !matches!(result, StepResult::Faulted(_)) && self.timer_reload != 0 {
+            self.timer_remaining = self.timer_remaining.wrapping_sub(1);
+            if self.timer_remaining == 0 {
+                self.fault(Self::TIMER_FAULT_REASON);
+                self.timer_remaining = self.timer_reload;
+                result = StepResult::Faulted(Self::TIMER_FAULT_REASON);
+            }
+        }
+
+        result
     }
 
-    pub fn run(&mut self) {
+    /// Runs until the program executes `HALT`, returning `StepResult::Halted`.
+    /// Unknown opcodes fault and resume at the `FAULT_VECTOR` rather than
+    /// stopping `run`, mirroring how `fault` already redirects the program
+    /// counter instead of unwinding out to the host.
+    pub fn run(&mut self) -> StepResult {
         self.flag_halt = false;
-        while !self.flag_halt {
-            self.step();
+        loop {
+            if let StepResult::Halted = self.step() {
+                return StepResult::Halted;
+            }
         }
     }
+
+    /// Runs up to `max_cycles` instructions, stopping early on `HALT`.
+    /// If the budget runs out first, execution stops without setting
+    /// `flag_halt`, so a later call can pick up exactly where this one left
+    /// off; the result of the last instruction executed is returned either
+    /// way. This keeps a runaway program from hanging the host.
+    pub fn run_limited(&mut self, max_cycles: u64) -> StepResult {
+        self.flag_halt = false;
+        let mut result = StepResult::Continue;
+        for _ in 0..max_cycles {
+            result = self.step();
+            if let StepResult::Halted = result {
+                return result;
+            }
+        }
+        result
+    }
+}
+
+/// The outcome of a single `Sma16::step`, mirroring what actually happened
+/// so callers (debuggers, single-steppers, host-side fault recovery) get a
+/// signal instead of having to infer it from mutated state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    /// The instruction executed normally.
+    Continue,
+    /// `HALT` executed; `Sma16::flag_halt` is now set.
+    Halted,
+    /// An unknown opcode, a stack overflow or underflow, or the countdown
+    /// timer faulted; execution resumes at `FAULT_VECTOR` with `reason`
+    /// readable from `INTERRUPT_REASON_REGISTER`.
+    Faulted(u16),
+    /// The instruction wrote to a memory-mapped output address.
+    Output(u16),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUSH: u16 = 0xE000;
+    const STACK_SIZE_REGISTER: u16 = 0x00D;
+    const TIMER_REGISTER: u16 = 0x00C;
+    const STACK_OVERFLOW_REASON: u16 = 0x0002;
+
+    #[test]
+    fn stack_size_register_reads_back_live_depth() {
+        let mut vm = Sma16::with_blank_memory(VecBus::new());
+        vm.write_memory(STACK_SIZE_REGISTER, 2);
+        vm.load_memory(0, &[PUSH, PUSH, PUSH]);
+
+        assert_eq!(vm.step(), StepResult::Continue);
+        assert_eq!(vm.read_memory(STACK_SIZE_REGISTER), 1);
+
+        assert_eq!(vm.step(), StepResult::Continue);
+        assert_eq!(vm.read_memory(STACK_SIZE_REGISTER), 2);
+
+        assert_eq!(vm.step(), StepResult::Faulted(STACK_OVERFLOW_REASON));
+        assert_eq!(vm.read_memory(STACK_SIZE_REGISTER), 2);
+    }
+
+    #[test]
+    fn instruction_fault_takes_priority_over_a_same_step_timer_fault() {
+        let mut vm = Sma16::with_blank_memory(VecBus::new());
+        vm.write_memory(STACK_SIZE_REGISTER, 0);
+        vm.write_memory(TIMER_REGISTER, 1);
+        vm.load_memory(0, &[PUSH]);
+
+        assert_eq!(vm.step(), StepResult::Faulted(STACK_OVERFLOW_REASON));
+    }
+
+    #[test]
+    fn ascii_out_and_small_out_land_in_the_bus() {
+        const ASCII_OUT: u16 = 0x00A;
+        const SMALL_OUT: u16 = 0x00B;
+
+        let mut vm = Sma16::with_blank_memory(VecBus::new());
+        vm.write_memory(ASCII_OUT, b'!' as u16);
+        assert_eq!(vm.bus.0, [b'!']);
+
+        // `A` then `B`, packed two-per-word as SMALL characters.
+        vm.write_memory(SMALL_OUT, 1 << 6);
+        assert_eq!(vm.bus.0, [b'!', b'A', b'B']);
+    }
 }